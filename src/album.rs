@@ -1,3 +1,5 @@
+use std::cmp::Ordering;
+
 use serde::de::{Deserialize, Deserializer};
 use serde_json;
 
@@ -7,10 +9,162 @@ use song;
 use sunk::Sunk;
 use util::*;
 
+/// The month component of an [`AlbumDate`], numbered so that a missing
+/// month sorts before any real one.
+///
+/// [`AlbumDate`]: struct.AlbumDate.html
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[repr(u8)]
+pub enum AlbumMonth {
+    None = 0,
+    January = 1,
+    February = 2,
+    March = 3,
+    April = 4,
+    May = 5,
+    June = 6,
+    July = 7,
+    August = 8,
+    September = 9,
+    October = 10,
+    November = 11,
+    December = 12,
+}
+
+impl AlbumMonth {
+    fn from_u32(n: u32) -> AlbumMonth {
+        use self::AlbumMonth::*;
+        match n {
+            1 => January,
+            2 => February,
+            3 => March,
+            4 => April,
+            5 => May,
+            6 => June,
+            7 => July,
+            8 => August,
+            9 => September,
+            10 => October,
+            11 => November,
+            12 => December,
+            _ => None,
+        }
+    }
+}
+
+/// A release date with possibly-missing precision, comparable so that a
+/// bare year sorts before a fully-dated release in the same year.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AlbumDate {
+    pub year: u32,
+    pub month: AlbumMonth,
+    pub day: u8,
+}
+
+impl AlbumDate {
+    fn new(year: u32, month: u32, day: u32) -> AlbumDate {
+        AlbumDate {
+            year,
+            month: AlbumMonth::from_u32(month),
+            day: day as u8,
+        }
+    }
+
+    /// Parses a `YYYY`, `YYYY-MM`, or `YYYY-MM-DD` date string, as used by
+    /// `originalReleaseDate`.
+    fn parse(raw: &str) -> Option<AlbumDate> {
+        let mut parts = raw.splitn(3, '-');
+        let year = parts.next()?.parse().ok()?;
+        let month = parts.next().and_then(|m| m.parse().ok()).unwrap_or(0);
+        let day = parts.next().and_then(|d| d.parse().ok()).unwrap_or(0);
+        Some(AlbumDate::new(year, month, day))
+    }
+
+    /// Parses the `YYYY-MM-DDTHH:MM:SS` prefix of a Subsonic `created`
+    /// timestamp.
+    fn parse_created(raw: &str) -> Option<AlbumDate> {
+        let date = raw.splitn(2, 'T').next()?;
+        AlbumDate::parse(date)
+    }
+}
+
+impl PartialOrd for AlbumDate {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for AlbumDate {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.year
+            .cmp(&other.year)
+            .then(self.month.cmp(&other.month))
+            .then(self.day.cmp(&other.day))
+    }
+}
+
+/// Manual tiebreaker for albums that would otherwise compare equal by
+/// [`AlbumDate`] and name, e.g. a remaster reissued the same day as the
+/// original.
+///
+/// [`AlbumDate`]: struct.AlbumDate.html
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct AlbumSeq(pub u32);
+
+/// A MusicBrainz identifier, scoped to the kind of entity it names.
+///
+/// Subsonic only ever hands back the bare id string, but an MBID is only
+/// useful once you know whether it names an artist, a release, a
+/// release group, or a recording, since each has its own MusicBrainz
+/// API endpoint. `Album::musicbrainz` and `Song::musicbrainz` are the
+/// only constructors in this crate: an album's `musicBrainzId` is a
+/// release MBID, while a song's is a recording MBID. `Artist` and
+/// `ReleaseGroup` aren't produced anywhere yet — Subsonic doesn't
+/// expose an artist- or release-group-scoped MBID on either — but are
+/// part of the type so a future accessor (e.g. on `Artist`) can return
+/// one without another breaking signature change.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MusicBrainz {
+    Artist(String),
+    Release(String),
+    ReleaseGroup(String),
+    Recording(String),
+}
+
+impl MusicBrainz {
+    pub fn id(&self) -> &str {
+        match *self {
+            MusicBrainz::Artist(ref id)
+            | MusicBrainz::Release(ref id)
+            | MusicBrainz::ReleaseGroup(ref id)
+            | MusicBrainz::Recording(ref id) => id,
+        }
+    }
+}
+
+/// Builds the MusicBrainz API URL to look up the release identified by
+/// `mbid` (as found on [`Album::musicbrainz`]), for cross-referencing the
+/// library against external metadata.
+///
+/// Deliberately descoped to URL-building only: it does not issue the
+/// request. `sunk`'s only HTTP client is `Sunk`, which speaks the
+/// Subsonic protocol against a configured server, not arbitrary URLs —
+/// actually fetching this would mean adding a general-purpose HTTP
+/// client dependency this crate doesn't otherwise need. Callers cross-
+/// referencing against MusicBrainz are expected to fetch this URL with
+/// their own client.
+///
+/// [`Album::musicbrainz`]: struct.Album.html#method.musicbrainz
+pub fn musicbrainz_release_lookup_url(mbid: &str) -> String {
+    format!("https://musicbrainz.org/ws/2/release/{}?fmt=json", mbid)
+}
+
 #[derive(Debug, Clone, Copy)]
 pub enum ListType {
     AlphaByArtist,
     AlphaByName,
+    ByGenre,
+    ByYear,
     Frequent,
     Highest,
     Newest,
@@ -25,6 +179,8 @@ impl ::std::fmt::Display for ListType {
         let fmt = match *self {
             AlphaByArtist => "alphabeticalByArtist",
             AlphaByName => "alphabeticalByName",
+            ByGenre => "byGenre",
+            ByYear => "byYear",
             Frequent => "frequent",
             Highest => "highest",
             Newest => "newest",
@@ -47,6 +203,11 @@ pub struct Album {
     pub year: Option<u64>,
     pub genre: Option<String>,
     pub song_count: u64,
+    pub date: AlbumDate,
+    pub seq: Option<AlbumSeq>,
+    musicbrainz: Option<MusicBrainz>,
+    sort_name: Option<String>,
+    artist_sort: Option<String>,
     songs: Vec<song::Song>,
 }
 
@@ -64,6 +225,10 @@ struct AlbumSerde {
     created: String,
     year: Option<u64>,
     genre: Option<String>,
+    originalReleaseDate: Option<String>,
+    musicBrainzId: Option<String>,
+    sortName: Option<String>,
+    artistSort: Option<String>,
     song: Option<Vec<song::Song>>,
 }
 
@@ -75,6 +240,28 @@ impl Album {
             Ok(self.songs.clone())
         }
     }
+
+    /// The release this album corresponds to on MusicBrainz, if the
+    /// server reported one. Unlike the numeric `id`, this key survives
+    /// retagging and is stable across Subsonic servers.
+    pub fn musicbrainz(&self) -> Option<&MusicBrainz> {
+        self.musicbrainz.as_ref()
+    }
+
+    /// The value to order this album on for a given alphabetical
+    /// `list_type`, preferring the server's sort name/artist-sort over
+    /// the display field, never mixing the two.
+    pub fn sort_key(&self, list_type: ListType) -> &str {
+        match list_type {
+            ListType::AlphaByArtist => self
+                .artist_sort
+                .as_ref()
+                .or_else(|| self.artist.as_ref())
+                .map(String::as_str)
+                .unwrap_or(""),
+            _ => self.sort_name.as_ref().unwrap_or(&self.name),
+        }
+    }
 }
 
 impl<'de> Deserialize<'de> for Album {
@@ -84,6 +271,14 @@ impl<'de> Deserialize<'de> for Album {
     {
         let raw = AlbumSerde::deserialize(de)?;
 
+        let date = raw
+            .originalReleaseDate
+            .as_ref()
+            .and_then(|d| AlbumDate::parse(d))
+            .or_else(|| AlbumDate::parse_created(&raw.created))
+            .or_else(|| raw.year.map(|y| AlbumDate::new(y as u32, 0, 0)))
+            .unwrap_or(AlbumDate::new(0, 0, 0));
+
         Ok(Album {
             id: raw.id.parse().unwrap(),
             name: raw.name,
@@ -94,6 +289,11 @@ impl<'de> Deserialize<'de> for Album {
             year: raw.year,
             genre: raw.genre,
             song_count: raw.songCount,
+            date,
+            seq: None,
+            musicbrainz: raw.musicBrainzId.map(MusicBrainz::Release),
+            sort_name: raw.sortName,
+            artist_sort: raw.artistSort,
             songs: raw.song.unwrap_or_default(),
         })
     }
@@ -104,18 +304,45 @@ pub fn get_album(sunk: &mut Sunk, id: u64) -> Result<Album> {
     Ok(serde_json::from_value::<Album>(res)?)
 }
 
+/// Fetches one page of `getAlbumList2`. The results are ordered however
+/// `list_type` itself orders them: the two alphabetical modes are
+/// re-sorted here by `Album::sort_key`, and every other mode — including
+/// `byYear`/`byGenre` and the ranked modes — is returned in the server's
+/// own order, untouched. This function does not impose a chronological
+/// `(AlbumDate, name)` ordering on top; use `AlbumIndex::iter_sorted` for
+/// that.
 pub fn get_albums(
     sunk: &mut Sunk,
     list_type: ListType,
     size: Option<u64>,
     offset: Option<u64>,
     folder_id: Option<u64>,
+    year_range: Option<(i64, i64)>,
+    genre: Option<&str>,
 ) -> Result<Vec<Album>> {
+    // `fromYear`/`toYear` are passed through verbatim: `byYear` treats
+    // `fromYear > toYear` as a request for descending order, so the
+    // bounds must not be normalized.
+    let (from_year, to_year) = match list_type {
+        ListType::ByYear => (
+            year_range.map(|(from, _)| from),
+            year_range.map(|(_, to)| to),
+        ),
+        _ => (None, None),
+    };
+    let genre = match list_type {
+        ListType::ByGenre => genre,
+        _ => None,
+    };
+
     let args = Query::new()
         .arg("type", list_type.to_string())
         .maybe_arg("size", map_str(size))
         .maybe_arg("offset", map_str(offset))
         .maybe_arg("musicFolderId", map_str(folder_id))
+        .maybe_arg("fromYear", map_str(from_year))
+        .maybe_arg("toYear", map_str(to_year))
+        .maybe_arg("genre", genre.map(String::from))
         .build();
 
     let res = sunk.get("getAlbumList2", args)?;
@@ -126,6 +353,15 @@ pub fn get_albums(
             albums.push(serde_json::from_value::<Album>(album)?);
         }
     }
+    // Only the two alphabetical modes are re-sorted here. Every other
+    // mode — `byYear`/`byGenre`, and the ranked modes like
+    // `highest`/`frequent`/`newest`/`recent`/`random`/`starred` — is
+    // defined entirely by the server's own ordering (rating, play
+    // count, recency, randomness, or an explicit descending year
+    // range), so a chronological resort would silently discard it.
+    if let ListType::AlphaByArtist | ListType::AlphaByName = list_type {
+        albums.sort_by(|a, b| a.sort_key(list_type).cmp(b.sort_key(list_type)));
+    }
     Ok(albums)
 }
 
@@ -137,9 +373,15 @@ mod tests {
     #[test]
     fn demo_get_albums() {
         let mut srv = test_util::demo_site().unwrap();
-        let albums =
-            get_albums(&mut srv, ListType::AlphaByArtist, None, None, None)
-                .unwrap();
+        let albums = get_albums(
+            &mut srv,
+            ListType::AlphaByArtist,
+            None,
+            None,
+            None,
+            None,
+            None,
+        ).unwrap();
 
         println!("{:?}", albums);
         assert!(!albums.is_empty())
@@ -163,6 +405,52 @@ mod tests {
         assert_eq!(parsed.songs[0].duration, 198);
     }
 
+    #[test]
+    fn album_sort_key_falls_back_to_display_fields() {
+        let parsed = serde_json::from_value::<Album>(raw()).unwrap();
+
+        assert_eq!(parsed.sort_key(ListType::AlphaByName), "Bellevue");
+        assert_eq!(
+            parsed.sort_key(ListType::AlphaByArtist),
+            "Misteur Valaire"
+        );
+    }
+
+    #[test]
+    fn album_musicbrainz_id() {
+        let parsed = serde_json::from_value::<Album>(raw()).unwrap();
+
+        assert_eq!(
+            parsed.musicbrainz(),
+            Some(&MusicBrainz::Release(String::from(
+                "0007058a-25c1-4704-9e2b-497b9b6fbbc4"
+            )))
+        );
+    }
+
+    #[test]
+    fn album_date_parsing() {
+        assert_eq!(AlbumDate::parse("2017"), Some(AlbumDate::new(2017, 0, 0)));
+        assert_eq!(
+            AlbumDate::parse("2017-03-12"),
+            Some(AlbumDate::new(2017, 3, 12))
+        );
+        assert_eq!(
+            AlbumDate::parse_created("2017-03-12T11:07:25.000Z"),
+            Some(AlbumDate::new(2017, 3, 12))
+        );
+    }
+
+    #[test]
+    fn album_date_ordering() {
+        let bare_year = AlbumDate::new(2017, 0, 0);
+        let full_date = AlbumDate::new(2017, 3, 12);
+        let next_year = AlbumDate::new(2018, 0, 0);
+
+        assert!(bare_year < full_date);
+        assert!(full_date < next_year);
+    }
+
     fn raw() -> serde_json::Value {
         json!({
          "id" : "1",
@@ -175,6 +463,7 @@ mod tests {
          "playCount" : 2223,
          "created" : "2017-03-12T11:07:25.000Z",
          "genre" : "(255)",
+         "musicBrainzId" : "0007058a-25c1-4704-9e2b-497b9b6fbbc4",
          "song" : [ {
             "id" : "27",
             "parent" : "25",
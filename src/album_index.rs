@@ -0,0 +1,187 @@
+//! An in-memory cache of the library's albums, kept warm by a background
+//! worker so repeated lookups don't round-trip to the server.
+
+use std::collections::HashMap;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+
+use album::{self, Album, ListType};
+use sunk::Sunk;
+
+/// How many albums `reindex` asks for per `getAlbumList2` call, so that a
+/// large library is rebuilt in bounded chunks rather than one giant
+/// request that blocks the worker.
+const REINDEX_BATCH_SIZE: u64 = 500;
+
+enum IndexCommand {
+    Reindex,
+    Exit,
+}
+
+#[derive(Default)]
+struct IndexSnapshot {
+    albums: HashMap<u64, Album>,
+}
+
+/// A background-refreshed cache of `Album`s, keyed by id.
+///
+/// Reads (`by_id`, `by_artist`, `iter_sorted`) come from the last
+/// completed snapshot. `by_artist` and `iter_sorted` never touch the
+/// network; `by_id` fetches the album with `getAlbum` on a miss. Call
+/// `trigger_reindex` after a mutation you know changed the library (e.g.
+/// a scan) to refresh it.
+pub struct AlbumIndex {
+    snapshot: Arc<Mutex<IndexSnapshot>>,
+    commands: Sender<IndexCommand>,
+    worker: Option<JoinHandle<()>>,
+    // Kept alongside the worker's own copy so `by_id` can hydrate a
+    // cache miss synchronously, without waiting on a reindex.
+    fetch: Mutex<Sunk>,
+}
+
+impl AlbumIndex {
+    /// Spawns the background worker and kicks off an initial reindex.
+    pub fn spawn(sunk: Sunk) -> AlbumIndex {
+        let snapshot = Arc::new(Mutex::new(IndexSnapshot::default()));
+        let (commands, rx) = mpsc::channel();
+        let worker_snapshot = Arc::clone(&snapshot);
+        let fetch = Mutex::new(sunk.clone());
+        let worker = thread::spawn(move || worker_loop(sunk, worker_snapshot, rx));
+
+        let index = AlbumIndex {
+            snapshot,
+            commands,
+            worker: Some(worker),
+            fetch,
+        };
+        index.trigger_reindex();
+        index
+    }
+
+    /// Asks the worker to rebuild the index from scratch. Returns
+    /// immediately; the refreshed snapshot becomes visible to reads once
+    /// the worker finishes the batch walk.
+    pub fn trigger_reindex(&self) {
+        let _ = self.commands.send(IndexCommand::Reindex);
+    }
+
+    /// Looks up an album by id from the cached snapshot, falling back to
+    /// `getAlbum` (and caching the result) on a miss.
+    pub fn by_id(&self, id: u64) -> Option<Album> {
+        if let Some(album) = self.snapshot.lock().unwrap().albums.get(&id).cloned() {
+            return Some(album);
+        }
+
+        let mut sunk = self.fetch.lock().unwrap();
+        let album = album::get_album(&mut sunk, id).ok()?;
+        self.snapshot
+            .lock()
+            .unwrap()
+            .albums
+            .insert(id, album.clone());
+        Some(album)
+    }
+
+    pub fn by_artist(&self, artist: &str) -> Vec<Album> {
+        self.snapshot
+            .lock()
+            .unwrap()
+            .albums
+            .values()
+            .filter(|a| a.artist.as_ref().map(String::as_str) == Some(artist))
+            .cloned()
+            .collect()
+    }
+
+    /// Albums matching `filter`, sorted by `(AlbumDate, name, AlbumSeq)`
+    /// — the last component only matters when two releases share an
+    /// identical date and name.
+    pub fn iter_sorted<F>(&self, filter: F) -> Vec<Album>
+    where
+        F: Fn(&Album) -> bool,
+    {
+        let mut albums: Vec<Album> = self
+            .snapshot
+            .lock()
+            .unwrap()
+            .albums
+            .values()
+            .filter(|a| filter(a))
+            .cloned()
+            .collect();
+        albums.sort_by(|a, b| {
+            a.date
+                .cmp(&b.date)
+                .then_with(|| a.name.cmp(&b.name))
+                .then_with(|| a.seq.cmp(&b.seq))
+        });
+        albums
+    }
+}
+
+impl Drop for AlbumIndex {
+    fn drop(&mut self) {
+        let _ = self.commands.send(IndexCommand::Exit);
+        if let Some(worker) = self.worker.take() {
+            let _ = worker.join();
+        }
+    }
+}
+
+fn worker_loop(
+    mut sunk: Sunk,
+    snapshot: Arc<Mutex<IndexSnapshot>>,
+    commands: Receiver<IndexCommand>,
+) {
+    for command in commands {
+        match command {
+            IndexCommand::Reindex => reindex(&mut sunk, &snapshot),
+            IndexCommand::Exit => break,
+        }
+    }
+}
+
+/// Walks `getAlbumList2` in `REINDEX_BATCH_SIZE`-sized pages and swaps
+/// the snapshot in once the whole library has been re-read, so readers
+/// never see a partially-rebuilt index.
+///
+/// `getAlbumList2` doesn't return each album's songs, so every album in
+/// a page is re-fetched with `getAlbum` to hydrate them before caching —
+/// otherwise every cached `Album` would have an empty `songs` and
+/// `Album::songs` would re-hit the server on every call, defeating the
+/// point of the cache.
+fn reindex(sunk: &mut Sunk, snapshot: &Arc<Mutex<IndexSnapshot>>) {
+    let mut offset = 0;
+    let mut rebuilt = HashMap::new();
+
+    loop {
+        let batch = match album::get_albums(
+            sunk,
+            ListType::AlphaByName,
+            Some(REINDEX_BATCH_SIZE),
+            Some(offset),
+            None,
+            None,
+            None,
+        ) {
+            Ok(batch) => batch,
+            Err(_) => return,
+        };
+        if batch.is_empty() {
+            break;
+        }
+
+        let fetched = batch.len() as u64;
+        for summary in batch {
+            let album = album::get_album(sunk, summary.id).unwrap_or(summary);
+            rebuilt.insert(album.id, album);
+        }
+        if fetched < REINDEX_BATCH_SIZE {
+            break;
+        }
+        offset += REINDEX_BATCH_SIZE;
+    }
+
+    snapshot.lock().unwrap().albums = rebuilt;
+}
@@ -0,0 +1,174 @@
+//! Opt-in expansion of a single CUE-backed audio file into its virtual
+//! per-track entries.
+//!
+//! Some libraries store a whole album as one audio file plus an external
+//! `.cue` sheet describing where each track starts. Subsonic then reports
+//! the album as a single `song::Song`, losing per-track titles and
+//! offsets. `expand` reconstructs the virtual tracks from the CUE sheet's
+//! contents so callers can present them like any other song.
+
+use song;
+
+/// CUE sheet timestamps are `MM:SS:FF`, at 75 frames per second.
+const CUE_FRAMES_PER_SECOND: u32 = 75;
+
+/// A single virtual track carved out of a CUE-backed `song::Song`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CueTrack {
+    pub id: String,
+    pub path: String,
+    pub title: Option<String>,
+    pub album: Option<String>,
+    pub artist: Option<String>,
+    pub genre: Option<String>,
+    pub track: u32,
+    pub duration: f64,
+}
+
+struct CueIndex {
+    track: u32,
+    title: Option<String>,
+    start: f64,
+}
+
+/// Splits `song` into its virtual CUE tracks, carrying over its album,
+/// artist, and genre metadata. The final track's duration runs to
+/// `song.duration`, since the CUE sheet only gives start offsets.
+pub fn expand(song: &song::Song, cue_sheet: &str) -> Vec<CueTrack> {
+    let indexes = parse_indexes(cue_sheet);
+    let base = base_path(&song.path);
+
+    indexes
+        .iter()
+        .enumerate()
+        .map(|(i, index)| {
+            let end = indexes
+                .get(i + 1)
+                .map(|next| next.start)
+                .unwrap_or(song.duration as f64);
+
+            CueTrack {
+                id: format!("{}/CUE_TRACK{:03}", base, index.track),
+                path: format!("{}/CUE_TRACK{:03}", base, index.track),
+                title: index.title.clone(),
+                album: song.album.clone(),
+                artist: song.artist.clone(),
+                genre: song.genre.clone(),
+                track: index.track,
+                duration: end - index.start,
+            }
+        })
+        .collect()
+}
+
+/// Strips the parent file's extension so virtual track ids don't inherit
+/// it, e.g. `Artist/Album/disc.flac` becomes `Artist/Album/disc`.
+fn base_path(path: &str) -> &str {
+    match path.rfind('.') {
+        Some(i) => &path[..i],
+        None => path,
+    }
+}
+
+/// Reads every `TRACK`'s `INDEX 01` (the actual start of the track,
+/// ignoring any `INDEX 00` pre-gap).
+fn parse_indexes(cue_sheet: &str) -> Vec<CueIndex> {
+    let mut indexes = Vec::new();
+    let mut current_track: Option<u32> = None;
+    let mut current_title: Option<String> = None;
+
+    for raw_line in cue_sheet.lines() {
+        let line = raw_line.trim();
+        if line.starts_with("TRACK ") {
+            current_track = line["TRACK ".len()..]
+                .split_whitespace()
+                .next()
+                .and_then(|n| n.parse().ok());
+            current_title = None;
+        } else if line.starts_with("TITLE ") {
+            current_title =
+                Some(line["TITLE ".len()..].trim_matches('"').to_string());
+        } else if line.starts_with("INDEX 01 ") {
+            if let (Some(track), Some(start)) = (
+                current_track,
+                parse_timestamp(&line["INDEX 01 ".len()..]),
+            ) {
+                indexes.push(CueIndex {
+                    track,
+                    title: current_title.clone(),
+                    start,
+                });
+            }
+        }
+    }
+
+    indexes
+}
+
+/// Converts a CUE `MM:SS:FF` timestamp into seconds.
+fn parse_timestamp(raw: &str) -> Option<f64> {
+    let mut parts = raw.trim().splitn(3, ':');
+    let minutes: f64 = parts.next()?.parse().ok()?;
+    let seconds: f64 = parts.next()?.parse().ok()?;
+    let frames: f64 = parts.next()?.parse().ok()?;
+    Some(minutes * 60.0 + seconds + frames / f64::from(CUE_FRAMES_PER_SECOND))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SHEET: &str = r#"FILE "album.flac" WAVE
+  TRACK 01 AUDIO
+    TITLE "Intro"
+    INDEX 00 00:00:00
+    INDEX 01 00:00:02
+  TRACK 02 AUDIO
+    TITLE "Main Theme"
+    INDEX 01 03:32:50
+  TRACK 03 AUDIO
+    TITLE "Outro"
+    INDEX 01 07:14:00
+"#;
+
+    fn song() -> song::Song {
+        ::serde_json::from_value(json!({
+            "id": "1",
+            "path": "Composer/Score/album.flac",
+            "album": "Score",
+            "artist": "Composer",
+            "duration": 600,
+            "title": "album",
+            "size": 0,
+            "contentType": "audio/flac",
+            "suffix": "flac",
+            "bitRate": 0
+        })).unwrap()
+    }
+
+    #[test]
+    fn splits_on_index_01_ignoring_pregap() {
+        let tracks = expand(&song(), SHEET);
+
+        assert_eq!(tracks.len(), 3);
+        assert_eq!(tracks[0].track, 1);
+        assert_eq!(tracks[0].title, Some(String::from("Intro")));
+        assert_eq!(tracks[0].id, "Composer/Score/album/CUE_TRACK001");
+    }
+
+    #[test]
+    fn computes_duration_from_successive_indexes() {
+        let tracks = expand(&song(), SHEET);
+
+        // `INDEX 01 00:00:02` is MM:SS:FF, so the "02" is 2 frames, not
+        // 2 seconds.
+        let track_1_start = 2.0 / 75.0;
+        let track_2_start = 3.0 * 60.0 + 32.0 + 50.0 / 75.0;
+        let track_3_start = 7.0 * 60.0 + 14.0;
+
+        assert!((tracks[0].duration - (track_2_start - track_1_start)).abs() < 1e-9);
+        assert!((tracks[1].duration - (track_3_start - track_2_start)).abs() < 1e-9);
+        // Final track runs to the parent file's total duration.
+        assert!((tracks[2].duration - (600.0 - track_3_start)).abs() < 1e-9);
+    }
+}
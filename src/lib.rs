@@ -0,0 +1,8 @@
+#[macro_use]
+extern crate serde_json;
+extern crate serde;
+
+pub mod album;
+pub mod album_index;
+pub mod cue;
+pub mod song;
@@ -0,0 +1,84 @@
+use serde::de::{Deserialize, Deserializer};
+
+use album::MusicBrainz;
+
+#[derive(Debug, Clone, Default)]
+pub struct Song {
+    pub id: u64,
+    parent: Option<u64>,
+    pub title: String,
+    pub album: Option<String>,
+    pub artist: Option<String>,
+    pub track: Option<u64>,
+    pub genre: Option<String>,
+    cover_id: Option<String>,
+    pub size: u64,
+    pub content_type: String,
+    pub suffix: String,
+    pub duration: u64,
+    pub bit_rate: u64,
+    pub path: String,
+    album_id: Option<u64>,
+    artist_id: Option<u64>,
+    musicbrainz: Option<MusicBrainz>,
+}
+
+/// Internal struct matching exactly what `serde` expects.
+#[derive(Debug, Deserialize)]
+#[allow(non_snake_case)]
+struct SongSerde {
+    id: String,
+    parent: Option<String>,
+    title: String,
+    album: Option<String>,
+    artist: Option<String>,
+    track: Option<u64>,
+    genre: Option<String>,
+    coverArt: Option<String>,
+    size: u64,
+    contentType: String,
+    suffix: String,
+    duration: u64,
+    bitRate: u64,
+    path: String,
+    albumId: Option<String>,
+    artistId: Option<String>,
+    musicBrainzId: Option<String>,
+}
+
+impl Song {
+    /// The recording this song corresponds to on MusicBrainz, if the
+    /// server reported one.
+    pub fn musicbrainz(&self) -> Option<&MusicBrainz> {
+        self.musicbrainz.as_ref()
+    }
+}
+
+impl<'de> Deserialize<'de> for Song {
+    fn deserialize<D>(de: D) -> ::std::result::Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let raw = SongSerde::deserialize(de)?;
+
+        Ok(Song {
+            id: raw.id.parse().unwrap(),
+            parent: raw.parent.map(|p| p.parse().unwrap()),
+            title: raw.title,
+            album: raw.album,
+            artist: raw.artist,
+            track: raw.track,
+            genre: raw.genre,
+            cover_id: raw.coverArt,
+            size: raw.size,
+            content_type: raw.contentType,
+            suffix: raw.suffix,
+            duration: raw.duration,
+            bit_rate: raw.bitRate,
+            path: raw.path,
+            album_id: raw.albumId.map(|i| i.parse().unwrap()),
+            artist_id: raw.artistId.map(|i| i.parse().unwrap()),
+            musicbrainz: raw.musicBrainzId.map(MusicBrainz::Recording),
+        })
+    }
+}